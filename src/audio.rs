@@ -1,17 +1,20 @@
+use hound::{SampleFormat, WavSpec, WavWriter};
+use rand::Rng;
 use rodio::{OutputStream, dynamic_mixer, source::Source};
 use std::{collections::HashMap, fmt, path::Path, thread, time::Duration};
 
 use crate::{
     error::{Error::*, Result},
     instrumentation::{SampleFile, SampleSource},
-    pattern::{Amplitude, Steps},
+    pattern::{Amplitude, Instrument, Steps},
+    synth::{Synth, Waveform},
 };
 
 /// Number of playback channels.
-const CHANNELS: u16 = 1;
+pub(crate) const CHANNELS: u16 = 1;
 
 /// Sample rate of playback.
-const SAMPLE_RATE: u32 = 44_100;
+pub(crate) const SAMPLE_RATE: u32 = 44_100;
 
 /// Represents the playback tempo (beats per minute).
 #[derive(Debug, Eq, Hash, PartialEq)]
@@ -31,6 +34,11 @@ impl fmt::Display for Tempo {
 }
 
 impl Tempo {
+    /// Returns the tempo in beats per minute.
+    pub fn bpm(&self) -> u16 {
+        self.0
+    }
+
     /// Computes the duration of a step.
     pub fn step_duration(&self, beats: usize) -> Duration {
         Duration::from_secs_f32((beats as f32) * 15.0 / (self.0 as f32))
@@ -39,7 +47,7 @@ impl Tempo {
     /// Computes the duration to delay a mix with trailing silence when played on repeat.
     /// This is necessary so that playback of the next iteration begins at the end
     /// of the current iteration's measure instead of after its final non-silent step.
-    fn delay_pad_duration(&self) -> Duration {
+    pub(crate) fn delay_pad_duration(&self) -> Duration {
         self.step_duration(1).mul_f32(self.delay_factor()) * 1 as u32
     }
 
@@ -49,22 +57,106 @@ impl Tempo {
     }
 }
 
-pub struct Sources(HashMap<SampleSource, (Steps, Amplitude)>);
+/// A performance-interpretation layer applied when scheduling steps, turning the
+/// mechanical step grid into something closer to a live performance.
+#[derive(Debug, Clone, Copy)]
+pub struct Groove {
+    /// Fraction of a step duration that odd (off-beat) 16th steps are delayed by,
+    /// in the range [0, 0.66].
+    swing: f32,
+    /// Bound, as a fraction of a step duration, on random timing jitter applied to
+    /// every hit.
+    humanize: f32,
+}
+
+impl Groove {
+    /// Creates a groove with the swing and humanize amounts given. Swing is clamped
+    /// to [0, 0.66]; humanize is clamped to a non-negative fraction of a step.
+    pub fn new(swing: f32, humanize: f32) -> Groove {
+        Groove {
+            swing: swing.clamp(0.0, 0.66),
+            humanize: humanize.max(0.0),
+        }
+    }
+
+    /// Returns a groove with no swing or humanization, i.e. the rigid step grid.
+    pub fn none() -> Groove {
+        Groove::new(0.0, 0.0)
+    }
+
+    /// Computes the delay for step `i`, swinging odd (off-beat) steps later and
+    /// applying bounded random jitter on top, measured as a fraction of one step so
+    /// it neither vanishes on the downbeat nor grows with the step index. A delay that
+    /// would fall before zero is clamped to zero.
+    fn delay(&self, tempo: &Tempo, i: usize) -> Duration {
+        let step = tempo.step_duration(1);
+        let mut delay_secs = step.as_secs_f32() * (i as f32);
+
+        if i % 2 == 1 {
+            delay_secs += step.as_secs_f32() * self.swing;
+        }
+
+        if self.humanize > 0.0 {
+            let jitter = rand::thread_rng().gen_range(-self.humanize..=self.humanize);
+            delay_secs += step.as_secs_f32() * jitter;
+        }
+
+        Duration::from_secs_f32(delay_secs.max(0.0))
+    }
+
+    /// Applies bounded random jitter to a hit's velocity, scaled by `humanize`.
+    fn humanize_velocity(&self, velocity: u8) -> u8 {
+        if self.humanize <= 0.0 {
+            return velocity;
+        }
+
+        let jitter = rand::thread_rng().gen_range(-self.humanize..=self.humanize);
+        ((velocity as f32) * (1.0 + jitter)).round().clamp(0.0, 255.0) as u8
+    }
+}
+
+impl Default for Groove {
+    fn default() -> Groove {
+        Groove::none()
+    }
+}
+
+pub struct Sources(
+    HashMap<SampleSource, (Steps, Amplitude)>,
+    HashMap<Instrument, (Steps, Amplitude)>,
+);
 
 impl Sources {
-    /// Mixes the sources together using audio files found in the path given.
+    /// Mixes the sources together, playing the loaded audio of each sample source. Instruments
+    /// with no bound audio file are synthesized instead, using their step frequency.
+    /// `groove` shifts and humanizes each step's timing, turning the rigid step grid
+    /// into a more musical performance.
     pub fn mix(
         &self,
         tempo: &Tempo,
+        groove: &Groove,
     ) -> Result<Box<dyn Source<Item = i16> + Send>> {
         let (controller, mixer) = dynamic_mixer::mixer(CHANNELS, SAMPLE_RATE);
         for (sample_source, (steps, amplitude)) in self.0.iter() {
-            for (i, step) in steps.iter().enumerate() {
-                if !step {
+            for (i, (velocity, _frequency)) in steps.iter().enumerate() {
+                if *velocity == 0 {
                     continue;
                 }
-                let delay = tempo.step_duration(1) * (i as u32);
-                controller.add(sample_source.source.clone().amplify(amplitude.value()).delay(delay));
+                let delay = groove.delay(tempo, i);
+                let velocity = groove.humanize_velocity(*velocity);
+                let hit_amplitude = amplitude.value() * (velocity as f32 / 255.0);
+                controller.add(sample_source.source.clone().amplify(hit_amplitude).delay(delay));
+            }
+        }
+        for (_, (steps, amplitude)) in self.1.iter() {
+            for (i, (velocity, frequency)) in steps.iter().enumerate() {
+                if *velocity == 0 || *frequency <= 0.0 {
+                    continue;
+                }
+                let delay = groove.delay(tempo, i);
+                let velocity = groove.humanize_velocity(*velocity);
+                let synth = Synth::new(*frequency, velocity, tempo.step_duration(1), Waveform::Saw);
+                controller.add(synth.amplify(amplitude.value()).delay(delay));
             }
         }
         Ok(Box::new(mixer))
@@ -72,7 +164,10 @@ impl Sources {
 }
 
 /// A type that represents the fully bound and reduced tracks of a pattern.
-pub struct Tracks(HashMap<SampleFile, (Steps, Amplitude)>);
+pub struct Tracks(
+    HashMap<SampleFile, (Steps, Amplitude)>,
+    HashMap<Instrument, (Steps, Amplitude)>,
+);
 
 impl Tracks {
     /// Creates sources using audio files found in the path given.
@@ -84,11 +179,92 @@ impl Tracks {
                 (steps.clone(), amplitude.clone())
             );
         }
-        Ok(Sources(sample_map))
+        Ok(Sources(sample_map, self.1.clone()))
+    }
+
+    pub fn from(
+        sample_tracks: HashMap<SampleFile, (Steps, Amplitude)>,
+        synth_tracks: HashMap<Instrument, (Steps, Amplitude)>,
+    ) -> Tracks {
+        Tracks(sample_tracks, synth_tracks)
+    }
+
+    /// Renders a mixed pattern to an in-memory PCM buffer instead of a live audio device.
+    ///
+    /// The buffer holds exactly `step_duration(beats) * repeats` worth of samples at
+    /// `SAMPLE_RATE`/`CHANNELS`, padded and looped the same way [`play_repeat`] drives
+    /// the audio device, so offline renders and live playback stay in sync.
+    pub fn render(
+        &self,
+        samples_path: &Path,
+        tempo: &Tempo,
+        beats: usize,
+        repeats: usize,
+        groove: &Groove,
+    ) -> Result<Rendered> {
+        let mixed = self.sources(samples_path)?.mix(tempo, groove)?;
+
+        Ok(Rendered(
+            mixed
+                .delay(tempo.delay_pad_duration())
+                .take_duration(tempo.step_duration(beats))
+                .repeat_infinite()
+                .take(render_sample_count(tempo, beats, repeats))
+                .collect(),
+        ))
+    }
+}
+
+/// Computes the number of samples a [`Tracks::render`] call produces: exactly
+/// `step_duration(beats) * repeats` worth of samples at `SAMPLE_RATE`.
+fn render_sample_count(tempo: &Tempo, beats: usize, repeats: usize) -> usize {
+    (tempo.step_duration(beats).as_secs_f32() * SAMPLE_RATE as f32).round() as usize * repeats
+}
+
+/// An abstraction over rendered audio that lets callers inspect output deterministically
+/// without going through an audio device.
+pub trait Sound {
+    /// Returns the sample rate of the sound.
+    fn sample_rate(&self) -> u32;
+
+    /// Returns the number of samples in the sound.
+    fn len(&self) -> usize;
+
+    /// Returns the sample at the index given.
+    fn nth(&self, n: usize) -> i16;
+}
+
+/// A pattern rendered to an in-memory buffer of `i16` PCM samples.
+pub struct Rendered(Vec<i16>);
+
+impl Sound for Rendered {
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
     }
 
-    pub fn from(hash_map: HashMap<SampleFile, (Steps, Amplitude)>) -> Tracks {
-        Tracks(hash_map)
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn nth(&self, n: usize) -> i16 {
+        self.0[n]
+    }
+}
+
+impl Rendered {
+    /// Writes the rendered buffer to a WAV file at the path given.
+    pub fn write_wav(&self, path: &Path) -> Result<()> {
+        let spec = WavSpec {
+            channels: CHANNELS,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let mut writer = WavWriter::create(path, spec).map_err(|_| AudioRenderError())?;
+        for sample in self.0.iter() {
+            writer.write_sample(*sample).map_err(|_| AudioRenderError())?;
+        }
+        writer.finalize().map_err(|_| AudioRenderError())
     }
 }
 
@@ -139,3 +315,98 @@ pub fn play_once(
         Err(AudioDeviceError())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_groove_none_matches_rigid_grid() {
+        let tempo = Tempo::from(120);
+        let groove = Groove::none();
+
+        for i in 0..16 {
+            assert_eq!(groove.delay(&tempo, i), tempo.step_duration(1) * (i as u32));
+        }
+    }
+
+    #[test]
+    fn test_groove_swing_delays_only_odd_steps() {
+        let tempo = Tempo::from(120);
+        let groove = Groove::new(0.5, 0.0);
+        let step = tempo.step_duration(1);
+
+        assert_eq!(groove.delay(&tempo, 0), step * 0);
+        assert_eq!(groove.delay(&tempo, 1), step + step.mul_f32(0.5));
+        assert_eq!(groove.delay(&tempo, 2), step * 2);
+    }
+
+    #[test]
+    fn test_groove_swing_is_clamped() {
+        assert_eq!(Groove::new(10.0, 0.0).swing, 0.66);
+        assert_eq!(Groove::new(-1.0, 0.0).swing, 0.0);
+    }
+
+    #[test]
+    fn test_groove_humanize_stays_within_one_step_of_the_grid() {
+        let tempo = Tempo::from(120);
+        let groove = Groove::new(0.0, 0.02);
+        let step = tempo.step_duration(1);
+
+        // Jitter is a fraction of one step, so no delay, step 0 included, lands more
+        // than `humanize` of a step after its grid position.
+        for i in 0..16 {
+            let delay = groove.delay(&tempo, i);
+            let grid = step * (i as u32);
+            let bound = step.mul_f32(0.02);
+            assert!(
+                delay.as_secs_f32() <= grid.as_secs_f32() + bound.as_secs_f32() + f32::EPSILON,
+                "step {} delay {:?} exceeded one-step bound around {:?}",
+                i,
+                delay,
+                grid
+            );
+        }
+    }
+
+    #[test]
+    fn test_groove_humanize_velocity_stays_in_bounds() {
+        let groove = Groove::new(0.0, 0.1);
+
+        for _ in 0..100 {
+            let humanized = groove.humanize_velocity(200);
+            assert!(humanized >= 180 && humanized <= 220);
+        }
+
+        assert_eq!(Groove::none().humanize_velocity(200), 200);
+    }
+
+    #[test]
+    fn test_rendered_sound() {
+        let rendered = Rendered(vec![1, -2, 3]);
+
+        assert_eq!(rendered.sample_rate(), SAMPLE_RATE);
+        assert_eq!(rendered.len(), 3);
+        assert_eq!(rendered.nth(0), 1);
+        assert_eq!(rendered.nth(2), 3);
+    }
+
+    #[test]
+    fn test_render_sample_count() {
+        let tempo = Tempo::from(120);
+
+        // One measure (16 steps) at 120 BPM is 2 seconds, i.e. 88,200 samples.
+        assert_eq!(render_sample_count(&tempo, 16, 1), 88_200);
+        assert_eq!(render_sample_count(&tempo, 16, 3), 88_200 * 3);
+        assert_eq!(render_sample_count(&tempo, 0, 4), 0);
+    }
+
+    #[test]
+    fn test_tempo_bpm_and_step_duration() {
+        let tempo = Tempo::from(120);
+
+        assert_eq!(tempo.bpm(), 120);
+        assert_eq!(tempo.step_duration(1), Duration::from_secs_f32(15.0 / 120.0));
+        assert_eq!(tempo.step_duration(16), Duration::from_secs_f32(16.0 * 15.0 / 120.0));
+    }
+}
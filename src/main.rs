@@ -0,0 +1,326 @@
+mod audio;
+mod error;
+mod instrumentation;
+mod midi;
+mod pattern;
+mod song;
+mod steps;
+mod synth;
+
+pub use instrumentation::Instrumentation;
+
+use std::{env, path::PathBuf, process};
+
+use audio::{play_once, play_repeat, Groove, Tempo};
+use error::{Error::*, Result};
+use midi::GmPercussionMap;
+use pattern::Pattern;
+use song::Song;
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+/// Command-line options for a single playback/render invocation.
+struct Options {
+    pattern_path: PathBuf,
+    samples_path: PathBuf,
+    tempo: Tempo,
+    repeat: bool,
+    no_audio: bool,
+    out_path: Option<PathBuf>,
+    song: bool,
+    midi_path: Option<PathBuf>,
+    groove: Groove,
+}
+
+fn run() -> Result<()> {
+    let options = parse_args(env::args().skip(1).collect())?;
+
+    if options.song {
+        let song = Song::parse(&options.pattern_path)?;
+        let instrumentation = Instrumentation::parse(&options.samples_path)?;
+        return song.play(&options.samples_path, &instrumentation, &options.tempo, &options.groove);
+    }
+
+    let pattern = Pattern::parse(&options.pattern_path)?;
+
+    if let Some(midi_path) = &options.midi_path {
+        let bytes = pattern.to_midi(&options.tempo, &GmPercussionMap::default())?;
+        std::fs::write(midi_path, bytes)?;
+        return Ok(());
+    }
+
+    let instrumentation = Instrumentation::parse(&options.samples_path)?;
+    let beats = pattern.len();
+    let tracks = pattern.bind(instrumentation);
+
+    if options.no_audio {
+        // Render to an in-memory buffer instead of opening an audio device, so CI
+        // can exercise mixing on machines with no sound card.
+        let rendered = tracks.render(&options.samples_path, &options.tempo, beats, 1, &options.groove)?;
+        if let Some(out_path) = &options.out_path {
+            rendered.write_wav(out_path)?;
+        }
+        Ok(())
+    } else {
+        let source = tracks
+            .sources(&options.samples_path)?
+            .mix(&options.tempo, &options.groove)?;
+        if options.repeat {
+            play_repeat(&options.tempo, source, beats)
+        } else {
+            play_once(&options.tempo, source, beats)
+        }
+    }
+}
+
+/// Parses command-line arguments into [`Options`].
+///
+/// Usage: `rudiments <pattern> <samples-dir> [--tempo N] [--swing F] [--humanize F] [--repeat] [--no-audio [--out FILE]] [--midi FILE]`
+/// Usage: `rudiments --song <song> <samples-dir> [--tempo N] [--swing F] [--humanize F]`
+fn parse_args(args: Vec<String>) -> Result<Options> {
+    let mut pattern_path = None;
+    let mut samples_path = None;
+    let mut tempo: u16 = 120;
+    let mut repeat = false;
+    let mut no_audio = false;
+    let mut out_path = None;
+    let mut song = false;
+    let mut midi_path = None;
+    let mut swing: f32 = 0.0;
+    let mut humanize: f32 = 0.0;
+
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--tempo" => {
+                tempo = iter
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                    .ok_or_else(|| ArgumentError("--tempo requires a BPM value".into()))?;
+            }
+            "--swing" => {
+                swing = iter
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                    .ok_or_else(|| ArgumentError("--swing requires a fraction of a step".into()))?;
+            }
+            "--humanize" => {
+                humanize = iter
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                    .ok_or_else(|| ArgumentError("--humanize requires a fraction of a step".into()))?;
+            }
+            "--repeat" => repeat = true,
+            "--no-audio" => no_audio = true,
+            "--out" => out_path = Some(PathBuf::from(
+                iter.next().ok_or_else(|| ArgumentError("--out requires a file path".into()))?,
+            )),
+            "--song" => song = true,
+            "--midi" => midi_path = Some(PathBuf::from(
+                iter.next().ok_or_else(|| ArgumentError("--midi requires a file path".into()))?,
+            )),
+            _ if pattern_path.is_none() => pattern_path = Some(PathBuf::from(arg)),
+            _ if samples_path.is_none() => samples_path = Some(PathBuf::from(arg)),
+            _ => return Err(ArgumentError(format!("unexpected argument: {}", arg))),
+        }
+    }
+
+    if repeat && no_audio {
+        return Err(ArgumentError("--repeat plays forever and cannot be rendered to a file; use --no-audio on its own".into()));
+    }
+    if out_path.is_some() && !no_audio {
+        return Err(ArgumentError("--out requires --no-audio".into()));
+    }
+    if song && no_audio {
+        return Err(ArgumentError("a song arrangement cannot be rendered with --no-audio; play it live instead".into()));
+    }
+    if song && repeat {
+        return Err(ArgumentError("a song's sections already carry their own repeat counts; --repeat does not apply".into()));
+    }
+    if song && midi_path.is_some() {
+        return Err(ArgumentError("--midi exports a single pattern file, not a song arrangement".into()));
+    }
+    if midi_path.is_some() && (no_audio || repeat) {
+        return Err(ArgumentError("--midi exports a pattern directly and cannot be combined with --no-audio or --repeat".into()));
+    }
+
+    Ok(Options {
+        pattern_path: pattern_path.ok_or_else(|| ArgumentError("missing pattern file".into()))?,
+        samples_path: samples_path.ok_or_else(|| ArgumentError("missing samples directory".into()))?,
+        tempo: Tempo::from(tempo),
+        repeat,
+        no_audio,
+        out_path,
+        song,
+        midi_path,
+        groove: Groove::new(swing, humanize),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_args_defaults() {
+        let options = parse_args(vec!["pattern.rudiments".into(), "samples".into()]).unwrap();
+
+        assert_eq!(options.pattern_path, PathBuf::from("pattern.rudiments"));
+        assert_eq!(options.samples_path, PathBuf::from("samples"));
+        assert_eq!(options.tempo.bpm(), 120);
+        assert!(!options.repeat);
+        assert!(!options.no_audio);
+        assert_eq!(options.out_path, None);
+        assert!(!options.song);
+        assert_eq!(options.midi_path, None);
+    }
+
+    #[test]
+    fn test_parse_args_no_audio_with_out() {
+        let options = parse_args(vec![
+            "pattern.rudiments".into(),
+            "samples".into(),
+            "--tempo".into(),
+            "128".into(),
+            "--no-audio".into(),
+            "--out".into(),
+            "out.wav".into(),
+        ])
+        .unwrap();
+
+        assert_eq!(options.tempo.bpm(), 128);
+        assert!(options.no_audio);
+        assert_eq!(options.out_path, Some(PathBuf::from("out.wav")));
+    }
+
+    #[test]
+    fn test_parse_args_missing_pattern() {
+        assert!(parse_args(vec![]).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_rejects_extra_positional() {
+        assert!(parse_args(vec!["a".into(), "b".into(), "c".into()]).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_rejects_repeat_with_no_audio() {
+        assert!(parse_args(vec![
+            "pattern.rudiments".into(),
+            "samples".into(),
+            "--repeat".into(),
+            "--no-audio".into(),
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_args_rejects_out_without_no_audio() {
+        assert!(parse_args(vec![
+            "pattern.rudiments".into(),
+            "samples".into(),
+            "--out".into(),
+            "out.wav".into(),
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_args_song() {
+        let options = parse_args(vec![
+            "--song".into(),
+            "arrangement.song".into(),
+            "samples".into(),
+        ])
+        .unwrap();
+
+        assert!(options.song);
+        assert_eq!(options.pattern_path, PathBuf::from("arrangement.song"));
+    }
+
+    #[test]
+    fn test_parse_args_rejects_song_with_no_audio() {
+        assert!(parse_args(vec![
+            "--song".into(),
+            "arrangement.song".into(),
+            "samples".into(),
+            "--no-audio".into(),
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_args_rejects_song_with_repeat() {
+        assert!(parse_args(vec![
+            "--song".into(),
+            "arrangement.song".into(),
+            "samples".into(),
+            "--repeat".into(),
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_args_midi() {
+        let options = parse_args(vec![
+            "pattern.rudiments".into(),
+            "samples".into(),
+            "--midi".into(),
+            "out.mid".into(),
+        ])
+        .unwrap();
+
+        assert_eq!(options.midi_path, Some(PathBuf::from("out.mid")));
+    }
+
+    #[test]
+    fn test_parse_args_rejects_midi_with_song() {
+        assert!(parse_args(vec![
+            "--song".into(),
+            "arrangement.song".into(),
+            "samples".into(),
+            "--midi".into(),
+            "out.mid".into(),
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_args_swing_and_humanize() {
+        let options = parse_args(vec![
+            "pattern.rudiments".into(),
+            "samples".into(),
+            "--swing".into(),
+            "0.2".into(),
+            "--humanize".into(),
+            "0.05".into(),
+        ])
+        .unwrap();
+
+        assert_eq!(format!("{:?}", options.groove), format!("{:?}", Groove::new(0.2, 0.05)));
+    }
+
+    #[test]
+    fn test_parse_args_defaults_to_no_groove() {
+        let options = parse_args(vec!["pattern.rudiments".into(), "samples".into()]).unwrap();
+
+        assert_eq!(format!("{:?}", options.groove), format!("{:?}", Groove::none()));
+    }
+
+    #[test]
+    fn test_parse_args_rejects_midi_with_no_audio() {
+        assert!(parse_args(vec![
+            "pattern.rudiments".into(),
+            "samples".into(),
+            "--midi".into(),
+            "out.mid".into(),
+            "--no-audio".into(),
+        ])
+        .is_err());
+    }
+}
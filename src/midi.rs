@@ -0,0 +1,266 @@
+use midly::{
+    num::{u15, u24, u28, u4, u7},
+    Format, Header, MetaMessage, MidiMessage, Smf, Timing, TrackEvent, TrackEventKind,
+};
+use std::collections::HashMap;
+
+use crate::{
+    audio::Tempo,
+    error::{Error::*, Result},
+    pattern::Pattern,
+};
+
+/// Pulses (ticks) per quarter note.
+const PPQ: u16 = 480;
+
+/// MIDI channel 10 (0-indexed as 9) is reserved for General MIDI percussion.
+const PERCUSSION_CHANNEL: u4 = u4::new(9);
+
+/// Duration, in ticks, of a note-on before its matching note-off.
+const NOTE_DURATION_TICKS: u32 = (PPQ as u32) / 8;
+
+/// General MIDI percussion key number instruments fall back to when nothing in a
+/// [`GmPercussionMap`] matches their name.
+const DEFAULT_NOTE: u8 = 36;
+
+/// The built-in name-to-note entries a [`GmPercussionMap::default`] is seeded with.
+const GM_PERCUSSION: &[(&str, u8)] = &[
+    ("kick", 36),
+    ("bass drum", 36),
+    ("snare", 38),
+    ("clap", 39),
+    ("closed hi-hat", 42),
+    ("hi-hat", 42),
+    ("hihat", 42),
+    ("open hi-hat", 46),
+    ("low tom", 45),
+    ("mid tom", 47),
+    ("high tom", 50),
+    ("crash", 49),
+    ("ride", 51),
+    ("cowbell", 56),
+    ("tambourine", 54),
+];
+
+/// A configurable mapping from instrument name to General MIDI percussion key number.
+///
+/// A name is first looked up verbatim (case-insensitively); if nothing matches, the
+/// longest configured name that appears as a substring wins, so a more specific entry
+/// like `"open hi-hat"` is preferred over the shorter `"hi-hat"` it contains. Names
+/// matching nothing fall back to Bass Drum 1 (key 36).
+pub struct GmPercussionMap(HashMap<String, u8>);
+
+impl GmPercussionMap {
+    /// Sets (or overrides) the note an instrument name maps to.
+    pub fn set(&mut self, name: &str, note: u8) {
+        self.0.insert(name.to_lowercase(), note);
+    }
+
+    /// Looks up the General MIDI percussion key number for an instrument name.
+    fn note_for(&self, name: &str) -> u7 {
+        let lower = name.to_lowercase();
+        let note = self.0.get(&lower).copied().unwrap_or_else(|| {
+            self.0
+                .iter()
+                .filter(|(candidate, _)| lower.contains(candidate.as_str()))
+                // Break ties between equal-length candidates deterministically, since
+                // HashMap iteration order is randomized per process.
+                .max_by_key(|(candidate, _)| (candidate.len(), candidate.as_str()))
+                .map(|(_, note)| *note)
+                .unwrap_or(DEFAULT_NOTE)
+        });
+        u7::new(note)
+    }
+}
+
+impl Default for GmPercussionMap {
+    /// Returns the built-in mapping covering common drum-kit instrument names.
+    fn default() -> GmPercussionMap {
+        GmPercussionMap(
+            GM_PERCUSSION
+                .iter()
+                .map(|(name, note)| (name.to_string(), *note))
+                .collect(),
+        )
+    }
+}
+
+/// Converts a tempo in beats per minute into a MIDI tempo meta-event value
+/// (microseconds per quarter note).
+fn micros_per_beat(tempo: &Tempo) -> u32 {
+    (60_000_000f64 / tempo.bpm() as f64).round() as u32
+}
+
+impl Pattern {
+    /// Exports the pattern as a Type-0 Standard MIDI File, with each instrument mapped
+    /// onto a General MIDI percussion key on channel 10 via `percussion_map`.
+    pub fn to_midi(&self, tempo: &Tempo, percussion_map: &GmPercussionMap) -> Result<Vec<u8>> {
+        let ticks_per_16th = (PPQ as u32) / 4;
+        let micros_per_beat = micros_per_beat(tempo);
+
+        // (tick, note, velocity, is_on)
+        let mut events: Vec<(u32, u7, u8, bool)> = Vec::new();
+
+        for (instrument, (steps, _amplitude)) in self.iter() {
+            let note = percussion_map.note_for(&instrument.to_string());
+
+            for (i, (velocity, _freq)) in steps.iter().enumerate() {
+                if *velocity == 0 {
+                    continue;
+                }
+                let tick_on = (i as u32) * ticks_per_16th;
+                events.push((tick_on, note, *velocity, true));
+                events.push((tick_on + NOTE_DURATION_TICKS, note, *velocity, false));
+            }
+        }
+
+        events.sort_by_key(|(tick, _, _, is_on)| (*tick, !*is_on));
+
+        let mut track = Vec::new();
+        track.push(TrackEvent {
+            delta: u28::new(0),
+            kind: TrackEventKind::Meta(MetaMessage::Tempo(u24::new(micros_per_beat))),
+        });
+
+        let mut last_tick = 0u32;
+        for (tick, note, velocity, is_on) in events {
+            let delta = tick - last_tick;
+            last_tick = tick;
+            let message = if is_on {
+                MidiMessage::NoteOn {
+                    key: note,
+                    vel: u7::new(velocity >> 1),
+                }
+            } else {
+                MidiMessage::NoteOff {
+                    key: note,
+                    vel: u7::new(0),
+                }
+            };
+            track.push(TrackEvent {
+                delta: u28::new(delta),
+                kind: TrackEventKind::Midi {
+                    channel: PERCUSSION_CHANNEL,
+                    message,
+                },
+            });
+        }
+
+        track.push(TrackEvent {
+            delta: u28::new(0),
+            kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+        });
+
+        let smf = Smf {
+            header: Header {
+                format: Format::SingleTrack,
+                timing: Timing::Metrical(u15::new(PPQ)),
+            },
+            tracks: vec![track],
+        };
+
+        let mut buf = Vec::new();
+        smf.write(&mut buf).map_err(|_| MidiExportError())?;
+        Ok(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_gm_percussion_map_exact_names() {
+        let map = GmPercussionMap::default();
+
+        assert_eq!(map.note_for("kick"), u7::new(36));
+        assert_eq!(map.note_for("Snare"), u7::new(38));
+        assert_eq!(map.note_for("HI-HAT"), u7::new(42));
+    }
+
+    #[test]
+    fn test_gm_percussion_map_prefers_longest_match() {
+        let map = GmPercussionMap::default();
+
+        // "open hi-hat" contains "hi-hat" as a substring; the longer, more specific
+        // entry must win rather than the first (shorter) one found.
+        assert_eq!(map.note_for("open hi-hat"), u7::new(46));
+        assert_eq!(map.note_for("my open hi-hat mic"), u7::new(46));
+        assert_eq!(map.note_for("closed hi-hat"), u7::new(42));
+    }
+
+    #[test]
+    fn test_gm_percussion_map_breaks_equal_length_ties_deterministically() {
+        let map = GmPercussionMap::default();
+
+        // "kick" and "ride" are both length-4 substrings of this name; the result
+        // must not depend on HashMap iteration order.
+        let expected = map.note_for("kick-ride-fill");
+        for _ in 0..10 {
+            assert_eq!(map.note_for("kick-ride-fill"), expected);
+        }
+    }
+
+    #[test]
+    fn test_gm_percussion_map_falls_back_to_default_note() {
+        let map = GmPercussionMap::default();
+
+        assert_eq!(map.note_for("tabla"), u7::new(DEFAULT_NOTE));
+    }
+
+    #[test]
+    fn test_gm_percussion_map_set_overrides() {
+        let mut map = GmPercussionMap::default();
+        map.set("kick", 35);
+        map.set("808", 60);
+
+        assert_eq!(map.note_for("kick"), u7::new(35));
+        assert_eq!(map.note_for("808"), u7::new(60));
+    }
+
+    #[test]
+    fn test_micros_per_beat() {
+        assert_eq!(micros_per_beat(&Tempo::from(120)), 500_000);
+        assert_eq!(micros_per_beat(&Tempo::from(60)), 1_000_000);
+    }
+
+    #[test]
+    fn test_to_midi_round_trips_note_events() {
+        let path = std::env::temp_dir().join("test_to_midi_round_trips_note_events.rudiments");
+        std::fs::write(&path, "kick |X---|----|----|----|\n").unwrap();
+        let pattern = Pattern::parse(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let map = GmPercussionMap::default();
+        let bytes = pattern.to_midi(&Tempo::from(120), &map).unwrap();
+
+        let smf = Smf::parse(&bytes).unwrap();
+        assert_eq!(smf.tracks.len(), 1);
+
+        let note_on = smf.tracks[0]
+            .iter()
+            .find_map(|event| match event.kind {
+                TrackEventKind::Midi {
+                    channel,
+                    message: MidiMessage::NoteOn { key, vel },
+                } => Some((channel, key, vel)),
+                _ => None,
+            })
+            .expect("expected a Note On event");
+
+        assert_eq!(note_on.0, PERCUSSION_CHANNEL);
+        assert_eq!(note_on.1, map.note_for("kick"));
+        assert_eq!(note_on.2, u7::new(255 >> 1));
+
+        let note_off_present = smf.tracks[0].iter().any(|event| {
+            matches!(
+                event.kind,
+                TrackEventKind::Midi {
+                    message: MidiMessage::NoteOff { .. },
+                    ..
+                }
+            )
+        });
+        assert!(note_off_present);
+    }
+}
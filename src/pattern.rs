@@ -10,7 +10,7 @@ use nom::{
     IResult,
 };
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt,
     fs::File,
     io::{BufRead, BufReader},
@@ -27,12 +27,35 @@ use crate::{
     steps::Steps,
 };
 
-/// Indicates a *play* step.
+/// Indicates an *accented* (full velocity) step.
+const STEP_ACCENT: &str = "X";
+
+/// Indicates a *play* (normal velocity) step.
 const STEP_PLAY: &str = "x";
 
+/// Indicates a *ghost* (low velocity) step.
+const STEP_GHOST: &str = "o";
+
+/// An alternate glyph for a *ghost* step.
+const STEP_GHOST_DOT: &str = ".";
+
 /// Indicates a *silent* step.
 const STEP_SILENT: &str = "-";
 
+/// MIDI velocity of an accented step.
+const VELOCITY_ACCENT: u8 = 255;
+
+/// MIDI velocity of a normal step.
+const VELOCITY_PLAY: u8 = 190;
+
+/// MIDI velocity of a ghost step.
+const VELOCITY_GHOST: u8 = 70;
+
+/// Converts a `0`-`9` digit glyph to a MIDI velocity, scaled linearly to [0, 255].
+fn digit_velocity(digit: u8) -> u8 {
+    ((digit as f32 / 9.0) * (VELOCITY_ACCENT as f32)).round() as u8
+}
+
 /// The beat separator in a step sequence.
 const SEPARATOR: &str = "|";
 
@@ -61,7 +84,9 @@ const NOTE_AF: &str = "Ab";
 /// of tracks in a pattern. A track contains an instrument name, a 16-step sequence,
 /// and an optional amplitude. The instrument name is an identifier and can only
 /// appear once per pattern. Each sequence represents a single measure in 4/4 time
-/// divided into 16th note steps (`x` for *play* and `-` for *silent*).
+/// divided into 16th note steps. A step may carry its own dynamics: `X` for an
+/// *accented* (full velocity) hit, `x` for a *normal* hit, `o` or `.` for a *ghost*
+/// (low velocity) hit, a digit `0`-`9` for an explicit velocity, and `-` for *silent*.
 /// A track may optionally include an amplitude in the range of [0,1] inclusive.
 /// By default, a track plays at full volume.
 ///
@@ -107,6 +132,11 @@ impl Pattern {
         self.0.get(i)
     }
 
+    /// Returns an iterator over the pattern's instruments and their step sequences.
+    pub fn iter(&self) -> impl Iterator<Item = (&Instrument, &(Steps, Amplitude))> {
+        self.0.iter()
+    }
+
     pub fn len(&self) -> usize {
         let mut max_len: usize = 0;
         for (_, (s, _)) in self.0.iter() {
@@ -120,32 +150,46 @@ impl Pattern {
     /// Binds a pattern's step sequences to audio files.
     /// Any sequences bound to the same audio file will be unioned.
     /// The smallest amplitude for instruments bound to the same audio file will be used.
+    /// Instruments with no bound audio file are kept separately so they can be
+    /// synthesized instead.
     pub fn bind(&self, instrumentation: Instrumentation) -> Tracks {
         let mut aggregate_steps = Steps::zeros(self.len());
-        Tracks::from(
-            instrumentation
-                .into_iter()
-                .map(|(sample_file, instruments)| {
-                    let simplified_steps = instruments.iter().fold(
-                        (Steps::zeros(self.len()), Amplitude::max()),
-                        |mut acc, instrument| {
-                            if let Some((steps, amplitude)) = self.get(instrument) {
-                                // update the aggregate step sequence
-                                aggregate_steps = aggregate_steps.union(steps);
-
-                                // update the track's step sequence and amplitude
-                                acc.0 = acc.0.union(steps);
-                                acc.1 = acc.1.min(amplitude);
-                            }
-
-                            acc
-                        },
-                    );
-
-                    (sample_file, simplified_steps)
-                })
-                .collect()
-        )
+        let mut bound: HashSet<Instrument> = HashSet::new();
+
+        let sample_tracks: HashMap<_, (Steps, Amplitude)> = instrumentation
+            .into_iter()
+            .map(|(sample_file, instruments)| {
+                let simplified_steps = instruments.iter().fold(
+                    (Steps::zeros(self.len()), Amplitude::max()),
+                    |mut acc, instrument| {
+                        if let Some((steps, amplitude)) = self.get(instrument) {
+                            // update the aggregate step sequence
+                            aggregate_steps = aggregate_steps.union(steps);
+
+                            // update the track's step sequence and amplitude
+                            acc.0 = acc.0.union(steps);
+                            acc.1 = acc.1.min(amplitude);
+                            bound.insert(instrument.clone());
+                        }
+
+                        acc
+                    },
+                );
+
+                (sample_file, simplified_steps)
+            })
+            .collect();
+
+        let synth_tracks: HashMap<Instrument, (Steps, Amplitude)> = self
+            .0
+            .iter()
+            .filter(|(instrument, _)| !bound.contains(instrument))
+            .map(|(instrument, (steps, amplitude))| {
+                (instrument.clone(), (steps.clone(), amplitude.clone()))
+            })
+            .collect();
+
+        Tracks::from(sample_tracks, synth_tracks)
     }
 }
 
@@ -160,7 +204,7 @@ impl fmt::Display for Pattern {
 }
 
 /// Represents a track's instrument name.
-#[derive(Debug, Eq, Hash, PartialEq)]
+#[derive(Debug, Eq, Hash, PartialEq, Clone)]
 pub struct Instrument(String);
 
 impl From<&str> for Instrument {
@@ -238,16 +282,27 @@ fn parse_instrument(s: &str) -> IResult<&str, &str> {
 /// Parses the steps from a track line.
 fn parse_steps(s: &str) -> IResult<&str, Steps> {
     let p = fold_many1(
-        alt((tag(STEP_PLAY), tag(STEP_SILENT), tag(SEPARATOR), tag(NOTE_A), tag(NOTE_B), tag(NOTE_C), tag(NOTE_D))),
+        alt((
+            tag(STEP_ACCENT), tag(STEP_PLAY), tag(STEP_GHOST), tag(STEP_GHOST_DOT), tag(STEP_SILENT),
+            tag(SEPARATOR), tag(NOTE_A), tag(NOTE_B), tag(NOTE_C), tag(NOTE_D),
+            tag("0"), tag("1"), tag("2"), tag("3"), tag("4"), tag("5"), tag("6"), tag("7"), tag("8"), tag("9"),
+        )),
         || Steps::new(),
         |mut acc: Steps, i| {
             match i {
-                STEP_PLAY => acc.push(255, 440.0),
+                STEP_ACCENT => acc.push(VELOCITY_ACCENT, 440.0),
+                STEP_PLAY => acc.push(VELOCITY_PLAY, 440.0),
+                STEP_GHOST | STEP_GHOST_DOT => acc.push(VELOCITY_GHOST, 440.0),
                 STEP_SILENT => acc.push(0, 0.0),
-                NOTE_A => acc.push(255, 440.0),
-                NOTE_B => acc.push(255, 493.88),
-                NOTE_C => acc.push(255, 523.25),
+                NOTE_A => acc.push(VELOCITY_ACCENT, 440.0),
+                NOTE_B => acc.push(VELOCITY_ACCENT, 493.88),
+                NOTE_C => acc.push(VELOCITY_ACCENT, 523.25),
                 NOTE_D => acc.push(0x3f, 587.33),
+                SEPARATOR => (),
+                "0" => acc.push(0, 0.0),
+                digit if digit.as_bytes()[0].is_ascii_digit() => {
+                    acc.push(digit_velocity(digit.parse().unwrap()), 440.0)
+                }
                 _ => (),
             }
             acc
@@ -321,14 +376,35 @@ mod tests {
             parse_steps(s4).unwrap(),
             ("", Steps::from(bitvec![0; 16]))
         );
-        assert_eq!(
-            parse_steps(s5).unwrap(),
-            ("", Steps::from(bitvec![1; 16]))
-        );
-        assert_eq!(
-            parse_steps(s6).unwrap(),
-            ("", Steps::from(bitvec![1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0]))
-        );
+        let mut all_play = Steps::new();
+        for _ in 0..16 {
+            all_play.push(VELOCITY_PLAY, 440.0);
+        }
+        assert_eq!(parse_steps(s5).unwrap(), ("", all_play));
+
+        let mut alternating = Steps::new();
+        for _ in 0..8 {
+            alternating.push(VELOCITY_PLAY, 440.0);
+            alternating.push(0, 0.0);
+        }
+        assert_eq!(parse_steps(s6).unwrap(), ("", alternating));
+    }
+
+    #[test]
+    fn test_parse_steps_dynamics() {
+        let s = "|X-o.|5---|----|----|";
+
+        let mut expected = Steps::new();
+        expected.push(VELOCITY_ACCENT, 440.0);
+        expected.push(0, 0.0);
+        expected.push(VELOCITY_GHOST, 440.0);
+        expected.push(VELOCITY_GHOST, 440.0);
+        expected.push(digit_velocity(5), 440.0);
+        for _ in 0..11 {
+            expected.push(0, 0.0);
+        }
+
+        assert_eq!(parse_steps(s).unwrap(), ("", expected));
     }
 
     #[test]
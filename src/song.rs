@@ -0,0 +1,244 @@
+extern crate nom;
+
+use nom::{
+    bytes::complete::{is_not, tag},
+    character::complete::{char, digit1, space0, space1},
+    combinator::{all_consuming, cut, map_res, opt},
+    IResult,
+};
+use rodio::{dynamic_mixer, source::Source, OutputStream};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    audio::{Groove, Tempo, CHANNELS, SAMPLE_RATE},
+    error::{Error::*, Result},
+    instrumentation::Instrumentation,
+    pattern::Pattern,
+};
+
+/// Indicates the repeat count prefix in a song section (e.g. `x4`).
+const REPEAT_PREFIX: &str = "x";
+
+/// Indicates the per-section tempo override prefix (e.g. `@120`).
+const TEMPO_PREFIX: char = '@';
+
+/// One line of a song file: a pattern file played a number of times, optionally at
+/// its own tempo.
+struct Section {
+    pattern_path: PathBuf,
+    repeats: usize,
+    tempo: Option<Tempo>,
+}
+
+/// Represents a multi-pattern arrangement: a sequence of named pattern files, each
+/// with a repeat count and an optional tempo, concatenated into one timeline.
+///
+/// # Example
+///
+/// ```text
+/// intro.rudiments x4 @120
+/// verse.rudiments x8 @128
+/// ```
+pub struct Song {
+    base_dir: PathBuf,
+    sections: Vec<Section>,
+}
+
+impl Song {
+    /// Parses a song file located at the path given.
+    pub fn parse(p: &Path) -> Result<Song> {
+        if !p.is_file() {
+            return Err(FileDoesNotExistError(p.into()));
+        }
+        let f = File::open(p)?;
+        let r = BufReader::new(f);
+
+        let mut sections = Vec::new();
+        for l in r.lines() {
+            let l = l?;
+            if l.trim().is_empty() {
+                continue;
+            }
+            match parse_section(&l[..]) {
+                Ok((_, section)) => sections.push(section),
+                _ => return Err(ParseError(l)),
+            }
+        }
+
+        Ok(Song {
+            base_dir: p.parent().map(Path::to_path_buf).unwrap_or_default(),
+            sections,
+        })
+    }
+
+    /// Mixes every section into a single timeline, each section's sources scheduled
+    /// at its cumulative start offset using that section's tempo (or `default_tempo`
+    /// when a section doesn't override it).
+    pub fn mix(
+        &self,
+        samples_path: &Path,
+        instrumentation: &Instrumentation,
+        default_tempo: &Tempo,
+        groove: &Groove,
+    ) -> Result<(Box<dyn Source<Item = i16> + Send>, Duration)> {
+        let (controller, mixer) = dynamic_mixer::mixer(CHANNELS, SAMPLE_RATE);
+        let mut offset = Duration::ZERO;
+
+        for section in self.sections.iter() {
+            let tempo = section.tempo.as_ref().unwrap_or(default_tempo);
+            let pattern = Pattern::parse(&self.base_dir.join(&section.pattern_path))?;
+            let beats = pattern.len();
+            let tracks = pattern.bind(instrumentation.clone());
+            let section_source = tracks.sources(samples_path)?.mix(tempo, groove)?;
+            let section_duration = tempo.step_duration(beats) * (section.repeats as u32);
+
+            controller.add(
+                section_source
+                    .delay(tempo.delay_pad_duration())
+                    .take_duration(tempo.step_duration(beats))
+                    .repeat_infinite()
+                    .take_duration(section_duration)
+                    .delay(offset),
+            );
+
+            offset += section_duration;
+        }
+
+        Ok((Box::new(mixer), offset))
+    }
+
+    /// Plays the whole song once, start to finish.
+    pub fn play(
+        &self,
+        samples_path: &Path,
+        instrumentation: &Instrumentation,
+        default_tempo: &Tempo,
+        groove: &Groove,
+    ) -> Result<()> {
+        let (source, duration) = self.mix(samples_path, instrumentation, default_tempo, groove)?;
+
+        if let Ok((_stream, stream_handle)) = OutputStream::try_default() {
+            if let Ok(()) = stream_handle.play_raw(source.convert_samples()) {
+                thread::sleep(duration);
+                Ok(())
+            } else {
+                Err(AudioDeviceError())
+            }
+        } else {
+            Err(AudioDeviceError())
+        }
+    }
+}
+
+/// Parses a pattern file path from a song line.
+fn parse_path(s: &str) -> IResult<&str, &str> {
+    is_not(" \t")(s)
+}
+
+/// Parses a repeat count (e.g. `x4`) from a song line. A count that overflows
+/// `usize` is a malformed song file rather than a silent default.
+fn parse_repeats(s: &str) -> IResult<&str, usize> {
+    let (s, _) = tag(REPEAT_PREFIX)(s)?;
+    map_res(digit1, str::parse)(s)
+}
+
+/// Parses an optional tempo override (e.g. `@120`) from a song line. A BPM that
+/// overflows `u16` is a malformed song file rather than a silent default; once the
+/// `@` prefix is seen, a bad digit run is `cut` so it isn't swallowed as "absent".
+fn parse_tempo(s: &str) -> IResult<&str, Option<u16>> {
+    opt(|i| {
+        let (i, _) = char(TEMPO_PREFIX)(i)?;
+        cut(map_res(digit1, str::parse))(i)
+    })(s)
+}
+
+/// Parses a single song section line.
+fn parse_section(s: &str) -> IResult<&str, Section> {
+    let (s, _) = space0(s)?;
+    let (s, path) = parse_path(s)?;
+    let (s, _) = space1(s)?;
+    let (s, repeats) = parse_repeats(s)?;
+    let (s, _) = space0(s)?;
+    let (s, tempo) = parse_tempo(s)?;
+    let (s, _) = all_consuming(space0)(s)?;
+
+    Ok((
+        s,
+        Section {
+            pattern_path: PathBuf::from(path),
+            repeats,
+            tempo: tempo.map(Tempo::from),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_repeats() {
+        assert_eq!(parse_repeats("x4").unwrap(), ("", 4));
+        assert_eq!(parse_repeats("x12 ").unwrap(), (" ", 12));
+        assert!(parse_repeats("4").is_err());
+        assert!(parse_repeats("x").is_err());
+    }
+
+    #[test]
+    fn test_parse_repeats_rejects_overflow() {
+        // One digit past usize::MAX must be a hard parse error, not a silent
+        // default of 1 repeat.
+        assert!(parse_repeats(&format!("x{}0", usize::MAX)).is_err());
+    }
+
+    #[test]
+    fn test_parse_tempo() {
+        assert_eq!(parse_tempo("@120").unwrap(), ("", Some(120)));
+        assert_eq!(parse_tempo("").unwrap(), ("", None));
+        assert_eq!(parse_tempo("abc").unwrap(), ("abc", None));
+    }
+
+    #[test]
+    fn test_parse_tempo_rejects_overflow() {
+        // One digit past u16::MAX must be a hard parse error, not a silent
+        // default of 120 BPM.
+        assert!(parse_tempo(&format!("@{}0", u16::MAX)).is_err());
+    }
+
+    #[test]
+    fn test_parse_section_with_tempo() {
+        let (rest, section) = parse_section("intro.rudiments x4 @120").unwrap();
+
+        assert_eq!(rest, "");
+        assert_eq!(section.pattern_path, PathBuf::from("intro.rudiments"));
+        assert_eq!(section.repeats, 4);
+        assert_eq!(section.tempo, Some(Tempo::from(120)));
+    }
+
+    #[test]
+    fn test_parse_section_without_tempo() {
+        let (rest, section) = parse_section("verse.rudiments x8").unwrap();
+
+        assert_eq!(rest, "");
+        assert_eq!(section.pattern_path, PathBuf::from("verse.rudiments"));
+        assert_eq!(section.repeats, 8);
+        assert_eq!(section.tempo, None);
+    }
+
+    #[test]
+    fn test_parse_section_requires_repeats() {
+        assert!(parse_section("intro.rudiments").is_err());
+    }
+
+    #[test]
+    fn test_parse_section_rejects_overflowing_tempo() {
+        let line = format!("intro.rudiments x4 @{}0", u16::MAX);
+        assert!(parse_section(&line).is_err());
+    }
+}
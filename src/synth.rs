@@ -0,0 +1,189 @@
+use rodio::source::Source;
+use std::time::Duration;
+
+use crate::audio::{CHANNELS, SAMPLE_RATE};
+
+/// Attack portion of a synthesized note's amplitude envelope.
+const ATTACK_SECONDS: f32 = 0.002;
+
+/// Decay portion of a synthesized note's amplitude envelope.
+const DECAY_SECONDS: f32 = 0.03;
+
+/// Sustain level a synthesized note decays to, as a fraction of peak amplitude.
+const SUSTAIN_LEVEL: f32 = 0.3;
+
+/// The waveform a [`Synth`] generates.
+#[derive(Debug, Clone, Copy)]
+pub enum Waveform {
+    Saw,
+    Square,
+}
+
+/// Computes the polyBLEP (band-limited step) correction for a phase and phase
+/// increment, used to anti-alias the discontinuity in a naive sawtooth.
+fn poly_blep(phase: f32, dt: f32) -> f32 {
+    if phase < dt {
+        let t = phase / dt;
+        2.0 * t - t * t - 1.0
+    } else if phase > 1.0 - dt {
+        let t = (phase - 1.0) / dt;
+        t * t + 2.0 * t + 1.0
+    } else {
+        0.0
+    }
+}
+
+/// A band-limited synthesized tone, generated from a step's frequency and velocity
+/// rather than a sample file, with a short attack-decay envelope so percussive hits
+/// don't click.
+pub struct Synth {
+    phase: f32,
+    dt: f32,
+    sample_index: u32,
+    total_samples: u32,
+    attack_samples: u32,
+    decay_samples: u32,
+    velocity_scale: f32,
+    waveform: Waveform,
+}
+
+impl Synth {
+    /// Creates a synthesized note at the frequency and velocity given, lasting no
+    /// longer than `duration`.
+    pub fn new(frequency: f32, velocity: u8, duration: Duration, waveform: Waveform) -> Synth {
+        Synth {
+            phase: 0.0,
+            dt: frequency / SAMPLE_RATE as f32,
+            sample_index: 0,
+            total_samples: (duration.as_secs_f32() * SAMPLE_RATE as f32) as u32,
+            attack_samples: (SAMPLE_RATE as f32 * ATTACK_SECONDS) as u32,
+            decay_samples: (SAMPLE_RATE as f32 * DECAY_SECONDS) as u32,
+            velocity_scale: velocity as f32 / 255.0,
+            waveform,
+        }
+    }
+
+    /// Generates one sample of an anti-aliased sawtooth at the current phase.
+    fn saw(&self, phase: f32) -> f32 {
+        (2.0 * phase - 1.0) - poly_blep(phase, self.dt)
+    }
+
+    /// Computes the attack-decay-sustain envelope at the current sample index.
+    fn envelope(&self) -> f32 {
+        if self.sample_index < self.attack_samples {
+            self.sample_index as f32 / self.attack_samples.max(1) as f32
+        } else if self.sample_index < self.attack_samples + self.decay_samples {
+            let t = (self.sample_index - self.attack_samples) as f32 / self.decay_samples.max(1) as f32;
+            1.0 - t * (1.0 - SUSTAIN_LEVEL)
+        } else {
+            SUSTAIN_LEVEL
+        }
+    }
+}
+
+impl Iterator for Synth {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if self.sample_index >= self.total_samples {
+            return None;
+        }
+
+        let value = match self.waveform {
+            Waveform::Saw => self.saw(self.phase),
+            // A square wave is the difference of two sawtooths a half-phase apart.
+            Waveform::Square => {
+                let shifted = (self.phase + 0.5) % 1.0;
+                self.saw(self.phase) - self.saw(shifted)
+            }
+        };
+
+        let sample = (value * self.envelope() * self.velocity_scale).clamp(-1.0, 1.0);
+
+        self.phase = (self.phase + self.dt) % 1.0;
+        self.sample_index += 1;
+
+        Some((sample * i16::MAX as f32) as i16)
+    }
+}
+
+impl Source for Synth {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        CHANNELS
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f32(
+            self.total_samples as f32 / SAMPLE_RATE as f32,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poly_blep_is_zero_away_from_discontinuity() {
+        assert_eq!(poly_blep(0.5, 0.01), 0.0);
+    }
+
+    #[test]
+    fn test_poly_blep_corrects_near_phase_wrap() {
+        let dt = 0.01;
+
+        // Just after wrapping to 0, and just before wrapping to 1, the correction
+        // should be non-zero and of opposite sign.
+        assert!(poly_blep(0.0, dt) < 0.0);
+        assert!(poly_blep(1.0 - dt / 2.0, dt) > 0.0);
+    }
+
+    #[test]
+    fn test_synth_length_matches_requested_duration() {
+        let duration = Duration::from_secs_f32(0.1);
+        let synth = Synth::new(440.0, 255, duration, Waveform::Saw);
+
+        assert_eq!(synth.count() as u32, (0.1 * SAMPLE_RATE as f32) as u32);
+    }
+
+    #[test]
+    fn test_synth_samples_stay_in_range() {
+        let synth = Synth::new(220.0, 255, Duration::from_secs_f32(0.05), Waveform::Square);
+
+        for sample in synth {
+            assert!(sample >= i16::MIN && sample <= i16::MAX);
+        }
+    }
+
+    #[test]
+    fn test_synth_envelope_attacks_then_decays_to_sustain() {
+        let mut synth = Synth::new(440.0, 255, Duration::from_secs_f32(1.0), Waveform::Saw);
+
+        assert_eq!(synth.envelope(), 0.0);
+
+        synth.sample_index = synth.attack_samples;
+        let post_attack = synth.envelope();
+        assert!((post_attack - 1.0).abs() < 1e-4);
+
+        synth.sample_index = synth.attack_samples + synth.decay_samples;
+        let sustained = synth.envelope();
+        assert!((sustained - SUSTAIN_LEVEL).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_synth_zero_velocity_is_silent() {
+        let synth = Synth::new(440.0, 0, Duration::from_secs_f32(0.01), Waveform::Saw);
+
+        for sample in synth {
+            assert_eq!(sample, 0);
+        }
+    }
+}